@@ -3,15 +3,20 @@ use r2d2_postgres::{TlsMode, PostgresConnectionManager};
 
 use errors::CoreError;
 
-pub fn get_db_pool() -> Result<Pool<PostgresConnectionManager>, CoreError> {
+// pooled connection type shared by every module that talks to storage
+pub type DbPool = Pool<PostgresConnectionManager>;
+
+// build the connection pool storage modules should `pool.get()` from,
+// instead of opening a fresh connection on every call
+pub fn get_db_pool(database_url: &str) -> Result<DbPool, CoreError> {
     let config = Config::default();
-    let manager = PostgresConnectionManager::new(
-        "postgres://mgul@localhost/blockchain",
-        TlsMode::None
-    ).unwrap();
+    let manager = match PostgresConnectionManager::new(database_url, TlsMode::None) {
+        Ok(manager) => manager,
+        Err(_) => return Err(CoreError::DatabaseError)
+    };
 
     match Pool::new(config, manager) {
         Ok(pool) => Ok(pool),
-        Err(e) => Err(CoreError::DatabaseError) // maybe just panic! as we can't establish a connection to database
+        Err(_) => Err(CoreError::DatabaseError)
     }
 }
\ No newline at end of file
@@ -0,0 +1,21 @@
+use std::fs::File;
+use std::io::Read;
+
+use errors::CoreError;
+
+#[derive(Deserialize)]
+pub struct Config {
+    pub database_url: String
+}
+
+// load settings (currently just the postgres connection string) from
+// config.json in the current working directory, instead of hardcoding them
+// in the code
+pub fn load() -> Result<Config, CoreError> {
+    let mut file = File::open("config.json")?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+
+    let config: Config = serde_json::from_str(&contents)?;
+    Ok(config)
+}
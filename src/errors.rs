@@ -0,0 +1,73 @@
+use std::io;
+
+use bincode;
+use base58::FromBase58Error;
+use hex::FromHexError;
+use postgres;
+use r2d2;
+use secp256k1;
+use serde_json;
+
+// shared error type threaded through every fallible call in the crate via `?`
+#[derive(Debug)]
+pub enum CoreError {
+    Io(io::Error),
+    Serialization(bincode::Error),
+    Secp256k1(secp256k1::Error),
+    Hex(FromHexError),
+    Base58(FromBase58Error),
+    Json(serde_json::Error),
+    Postgres(postgres::Error),
+    // the connection pool could not be built or could not hand out a connection
+    DatabaseError,
+    // a transaction failed signature/nonce validation on mempool admission
+    InvalidTransaction
+}
+
+impl From<io::Error> for CoreError {
+    fn from(e: io::Error) -> CoreError {
+        CoreError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for CoreError {
+    fn from(e: bincode::Error) -> CoreError {
+        CoreError::Serialization(e)
+    }
+}
+
+impl From<secp256k1::Error> for CoreError {
+    fn from(e: secp256k1::Error) -> CoreError {
+        CoreError::Secp256k1(e)
+    }
+}
+
+impl From<FromHexError> for CoreError {
+    fn from(e: FromHexError) -> CoreError {
+        CoreError::Hex(e)
+    }
+}
+
+impl From<FromBase58Error> for CoreError {
+    fn from(e: FromBase58Error) -> CoreError {
+        CoreError::Base58(e)
+    }
+}
+
+impl From<serde_json::Error> for CoreError {
+    fn from(e: serde_json::Error) -> CoreError {
+        CoreError::Json(e)
+    }
+}
+
+impl From<postgres::Error> for CoreError {
+    fn from(e: postgres::Error) -> CoreError {
+        CoreError::Postgres(e)
+    }
+}
+
+impl From<r2d2::GetTimeout> for CoreError {
+    fn from(_: r2d2::GetTimeout) -> CoreError {
+        CoreError::DatabaseError
+    }
+}
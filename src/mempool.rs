@@ -0,0 +1,193 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+
+use blockchain::DbPool;
+use errors::CoreError;
+use transactions;
+use transactions::Transaction;
+
+// max entries before lowest-fee eviction kicks in
+const DEFAULT_CAPACITY: usize = 5_000;
+
+// heap order: highest fee first, oldest timestamp breaks ties
+#[derive(Clone)]
+struct PendingTransaction(Transaction);
+
+impl PendingTransaction {
+    fn fee(&self) -> i32 {
+        self.0.transaction.content.fee
+    }
+
+    fn timestamp(&self) -> i64 {
+        self.0.transaction.content.timestamp
+    }
+}
+
+impl PartialEq for PendingTransaction {
+    fn eq(&self, other: &PendingTransaction) -> bool {
+        self.fee() == other.fee() && self.timestamp() == other.timestamp()
+    }
+}
+
+impl Eq for PendingTransaction {}
+
+impl PartialOrd for PendingTransaction {
+    fn partial_cmp(&self, other: &PendingTransaction) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PendingTransaction {
+    fn cmp(&self, other: &PendingTransaction) -> Ordering {
+        self.fee().cmp(&other.fee())
+            .then_with(|| other.timestamp().cmp(&self.timestamp()))
+    }
+}
+
+// in-memory, fee-ordered pool of pending transactions awaiting a block
+pub struct Mempool {
+    capacity: usize,
+    heap: BinaryHeap<PendingTransaction>
+}
+
+impl Mempool {
+    pub fn new(capacity: usize) -> Mempool {
+        Mempool {
+            capacity: capacity,
+            heap: BinaryHeap::new()
+        }
+    }
+
+    pub fn add(&mut self, tx: Transaction, db_pool: &DbPool) -> Result<(), CoreError> {
+        if !tx.validate_against_state(db_pool)? {
+            return Err(CoreError::InvalidTransaction);
+        }
+
+        // a pending tx with the same (sender, nonce) is a conflicting spend
+        if self.has_pending_nonce(&tx) {
+            return Err(CoreError::InvalidTransaction);
+        }
+
+        if self.heap.len() >= self.capacity {
+            self.evict_lowest_fee();
+        }
+
+        self.heap.push(PendingTransaction(tx));
+        Ok(())
+    }
+
+    fn has_pending_nonce(&self, tx: &Transaction) -> bool {
+        self.heap.iter().any(|pending| {
+            pending.0.transaction.content.sender_addr == tx.transaction.content.sender_addr
+                && pending.0.transaction.content.nonce == tx.transaction.content.nonce
+        })
+    }
+
+    // the `n` highest-priority transactions; stay in the pool until `remove`
+    pub fn take_best(&self, n: usize) -> Vec<Transaction> {
+        let mut ordered: Vec<&PendingTransaction> = self.heap.iter().collect();
+        ordered.sort_by(|a, b| b.cmp(a));
+
+        ordered.into_iter()
+            .take(n)
+            .map(|pending| pending.0.clone())
+            .collect()
+    }
+
+    // drop mined transactions, advancing each sender's nonce as they go
+    pub fn remove(&mut self, ids: &[Vec<u8>], pool: &DbPool) -> Result<(), CoreError> {
+        let ids: HashSet<&Vec<u8>> = ids.iter().collect();
+        let mut remaining: Vec<PendingTransaction> = Vec::new();
+
+        for pending in self.heap.drain().collect::<Vec<PendingTransaction>>() {
+            if ids.contains(&pending.0.id) {
+                transactions::advance_account_nonce(
+                    pool,
+                    &pending.0.transaction.content.sender_addr,
+                    pending.0.transaction.content.nonce
+                )?;
+            } else {
+                remaining.push(pending);
+            }
+        }
+
+        self.heap = remaining.into_iter().collect();
+        Ok(())
+    }
+
+    // take_best + remove in one call, for block producers
+    pub fn build_block(&mut self, n: usize, pool: &DbPool) -> Result<Vec<Transaction>, CoreError> {
+        let txs = self.take_best(n);
+        let ids: Vec<Vec<u8>> = txs.iter().map(|tx| tx.id.clone()).collect();
+        self.remove(&ids, pool)?;
+        Ok(txs)
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    fn evict_lowest_fee(&mut self) {
+        if self.heap.is_empty() {
+            return;
+        }
+
+        let mut pending: Vec<PendingTransaction> = self.heap.drain().collect();
+        pending.sort();
+        pending.remove(0);
+        self.heap = pending.into_iter().collect();
+    }
+}
+
+impl Default for Mempool {
+    fn default() -> Mempool {
+        Mempool::new(DEFAULT_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use transactions::{TransactionContent, TransactionSigned};
+
+    fn dummy_tx(sender: u8, nonce: u64, fee: i32, timestamp: i64) -> Transaction {
+        let content = TransactionContent {
+            sender_addr: vec![sender],
+            receiver_addr: vec![9],
+            amount: 1,
+            fee: fee,
+            nonce: nonce,
+            timestamp: timestamp
+        };
+
+        Transaction {
+            id: vec![sender, nonce as u8, fee as u8, timestamp as u8],
+            transaction: TransactionSigned { content: content, signature: vec![0; 65] }
+        }
+    }
+
+    #[test]
+    fn take_best_orders_by_fee_then_earlier_timestamp_first() {
+        let mut pool = Mempool::new(10);
+        pool.heap.push(PendingTransaction(dummy_tx(1, 0, 5, 100)));
+        pool.heap.push(PendingTransaction(dummy_tx(2, 0, 10, 200)));
+        pool.heap.push(PendingTransaction(dummy_tx(3, 0, 10, 50)));
+
+        let best = pool.take_best(3);
+        let senders: Vec<u8> = best.iter()
+            .map(|tx| tx.transaction.content.sender_addr[0])
+            .collect();
+
+        assert_eq!(senders, vec![3, 2, 1]);
+    }
+
+    #[test]
+    fn has_pending_nonce_only_matches_same_sender_and_nonce() {
+        let mut pool = Mempool::new(10);
+        pool.heap.push(PendingTransaction(dummy_tx(1, 0, 5, 100)));
+
+        assert!(pool.has_pending_nonce(&dummy_tx(1, 0, 1, 999)));
+        assert!(!pool.has_pending_nonce(&dummy_tx(1, 1, 1, 999)));
+        assert!(!pool.has_pending_nonce(&dummy_tx(2, 0, 1, 999)));
+    }
+}
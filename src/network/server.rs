@@ -0,0 +1,117 @@
+use std::sync::Mutex;
+
+use rouille::Response;
+
+use blockchain::{self, DbPool};
+use config;
+use errors::CoreError;
+use mempool::Mempool;
+use transactions;
+use transactions::Transaction;
+
+// address the REST API listens on
+const LISTEN_ADDR: &'static str = "0.0.0.0:8000";
+
+// transactions included per call to `POST /blocks/mine`
+const BLOCK_TX_CAPACITY: usize = 500;
+
+// log the error detail server-side; callers only get a generic message
+fn error_response(err: CoreError) -> Response {
+    eprintln!("REQUEST ERROR: {:?}", err);
+    Response::json(&json!({ "error": "internal error" })).with_status_code(500)
+}
+
+fn not_found_response(message: &str) -> Response {
+    Response::json(&json!({ "error": message })).with_status_code(404)
+}
+
+// GET /tx/:id - the JSON-serialized transaction looked up by hex id
+fn get_tx(pool: &DbPool, id: &str) -> Response {
+    match transactions::get_by_id(pool, id) {
+        Ok(Some(tx)) => Response::json(&tx),
+        Ok(None) => not_found_response("transaction not found"),
+        Err(e) => error_response(e)
+    }
+}
+
+// GET /address/:addr/txs - every transaction where the address is the
+// sender or the receiver
+fn get_address_txs(pool: &DbPool, addr: &str) -> Response {
+    match transactions::get_by_address(pool, addr) {
+        Ok(txs) => Response::json(&txs),
+        Err(e) => error_response(e)
+    }
+}
+
+// GET /address/:addr/balance - inbound minus outbound amount for the address
+fn get_address_balance(pool: &DbPool, addr: &str) -> Response {
+    match transactions::get_balance(pool, addr) {
+        Ok(balance) => Response::json(&json!({ "address": addr, "balance": balance })),
+        Err(e) => error_response(e)
+    }
+}
+
+// POST /tx - submit a raw signed transaction for mempool admission
+fn post_tx(pool: &DbPool, mempool: &Mutex<Mempool>, body: &mut Vec<u8>) -> Response {
+    let tx = match Transaction::from_bytes(body) {
+        Ok(tx) => tx,
+        Err(e) => return error_response(e)
+    };
+
+    let mut mempool = mempool.lock().unwrap();
+    match mempool.add(tx.clone(), pool) {
+        Ok(()) => match tx.store_db(pool) {
+            Ok(()) => Response::json(&json!({ "status": "accepted" })),
+            Err(e) => error_response(e)
+        },
+        Err(e) => error_response(e)
+    }
+}
+
+// POST /blocks/mine - pull the best pending transactions as a mined block
+fn post_mine_block(pool: &DbPool, mempool: &Mutex<Mempool>) -> Response {
+    let mut mempool = mempool.lock().unwrap();
+    match mempool.build_block(BLOCK_TX_CAPACITY, pool) {
+        Ok(txs) => Response::json(&txs),
+        Err(e) => error_response(e)
+    }
+}
+
+// start the REST API
+pub fn start() {
+    println!("START SERVER [REST]");
+
+    let config = config::load().expect("failed to load config.json");
+    let pool = blockchain::get_db_pool(&config.database_url)
+        .expect("failed to build database connection pool");
+    let mempool = Mutex::new(Mempool::default());
+
+    rouille::start_server(LISTEN_ADDR, move |request| {
+        router!(request,
+            (GET) (/tx/{id: String}) => {
+                get_tx(&pool, &id)
+            },
+            (GET) (/address/{addr: String}/txs) => {
+                get_address_txs(&pool, &addr)
+            },
+            (GET) (/address/{addr: String}/balance) => {
+                get_address_balance(&pool, &addr)
+            },
+            (POST) (/tx) => {
+                let mut body = Vec::new();
+                match request.data().and_then(|mut data| {
+                    use std::io::Read;
+                    data.read_to_end(&mut body).ok()
+                }) {
+                    Some(_) => post_tx(&pool, &mempool, &mut body),
+                    None => Response::json(&json!({ "error": "missing request body" }))
+                        .with_status_code(400)
+                }
+            },
+            (POST) (/blocks/mine) => {
+                post_mine_block(&pool, &mempool)
+            },
+            _ => Response::empty_404()
+        )
+    });
+}
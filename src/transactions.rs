@@ -4,43 +4,45 @@ use std::io::ErrorKind;
 use std::path::Path;
 use bincode::{serialize, deserialize, Infinite};
 use sha2::{Sha256, Digest};
-use rusqlite::Connection;
 use base58::{FromBase58, ToBase58};
 use hex::{FromHex, ToHex};
 use secp256k1;
-use secp256k1::key::{SecretKey, PublicKey};
 
+use blockchain::DbPool;
 use errors::CoreError;
 use utils;
+use wallet;
 
 // TODO FIXME fix struct privacy (too many pub)
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
 pub struct TransactionContent {
     pub sender_addr: Vec<u8>,
-    pub sender_pubkey: Vec<u8>,
     pub receiver_addr: Vec<u8>,
     pub amount: i32,
+    pub fee: i32,
+    pub nonce: u64,
     pub timestamp: i64
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
 pub struct TransactionSigned {
     pub content: TransactionContent,
-    signature: Vec<u8>
+    pub(crate) signature: Vec<u8>
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
 pub struct Transaction {
     pub id: Vec<u8>,
     pub transaction: TransactionSigned // bad field name...
 }
 
 impl TransactionContent {
-    // sign a transaction using schnorr signature
+    // sign a transaction using a recoverable ECDSA signature, so the sender's
+    // public key never has to be carried alongside the transaction
     pub fn get_signature(
         &self,
-        private_key: SecretKey
+        private_key: &wallet::SecretKeyBytes
     ) -> Result<Vec<u8>, CoreError> {
         println!("SIGN TRANSACTION");
 
@@ -56,8 +58,17 @@ impl TransactionContent {
         // create the input message with the hashed tx content
         let input = secp256k1::Message::from_slice(tx_content_hashed.as_slice())?;
 
-        // return the signature created with the input message and private key
-        Ok(secp.sign_schnorr(&input, &private_key)?.serialize())
+        // the parsed secret key is only reachable inside this signing closure;
+        // see `SecretKeyBytes::expose` for what is and is not zeroed
+        let recoverable_sig = private_key.expose(|secret_key| {
+            secp.sign_recoverable(&input, secret_key)
+        })??;
+        let (recovery_id, compact_sig) = recoverable_sig.serialize_compact(&secp);
+
+        let mut signature = compact_sig.to_vec();
+        signature.push(recovery_id.to_i32() as u8);
+
+        Ok(signature)
     }
 }
 
@@ -82,7 +93,8 @@ impl Transaction {
         Ok(tx)
     }
 
-    // verify a transaction using the signature and the public key
+    // verify a transaction by recovering the sender's public key from the
+    // signature and checking it hashes down to the claimed sender address
     pub fn verify(&self) -> Result<bool, CoreError> {
         println!("VERIFY TRANSACTION");
 
@@ -98,41 +110,56 @@ impl Transaction {
         // create the input message using the hashed tx content
         let input = secp256k1::Message::from_slice(tx_hashed.as_slice())?;
 
-        // retrieve sig and pbkey from the tx
-        let signature = secp256k1::schnorr::Signature::deserialize(&self.transaction.signature);
-        let public_key = PublicKey::from_slice(
-            &secp, &self.transaction.content.sender_pubkey
+        // the stored signature is a 64-byte compact sig plus a 1-byte recovery id
+        if self.transaction.signature.len() != 65 {
+            return Ok(false);
+        }
+        let (compact_sig, recovery_id) = self.transaction.signature.split_at(64);
+        let recovery_id = secp256k1::recovery::RecoveryId::from_i32(recovery_id[0] as i32)?;
+        let signature = secp256k1::recovery::RecoverableSignature::from_compact(
+            &secp, compact_sig, recovery_id
         )?;
 
-        // verify the input message using the signature and pbkey
-        Ok(
-            match secp.verify_schnorr(&input, &signature, &public_key) {
-                Ok(()) => true,
-                _ => false
-            }
-        )
+        // recover the public key instead of trusting one carried on the tx
+        let public_key = match secp.recover(&input, &signature) {
+            Ok(public_key) => public_key,
+            _ => return Ok(false)
+        };
+
+        // the recovered key must hash down to the address the tx claims to be from
+        Ok(wallet::derive_address(&public_key) == self.transaction.content.sender_addr)
+    }
+
+    // verify the signature, then check the sender's nonce against account
+    // state to reject transactions that have already been applied (replay)
+    pub fn validate_against_state(&self, pool: &DbPool) -> Result<bool, CoreError> {
+        if !self.verify()? {
+            return Ok(false);
+        }
+
+        let expected_nonce = get_account_nonce(pool, &self.transaction.content.sender_addr)?;
+        Ok(self.transaction.content.nonce == expected_nonce)
     }
 
     // store a transaction on database (cache) for further block creation
     // TODO rewrite this with redis
-    pub fn store_db(&self) -> Result<(), CoreError> {
+    pub fn store_db(&self, pool: &DbPool) -> Result<(), CoreError> {
         println!("STORE TRANSACTION [DB]");
-        // TODO rewrite this with connection pools
-        // TODO get the db address string from config.json
-        let conn = Connection::open("db/storage.db")?;
+        let conn = pool.get()?;
 
         let id = &self.id.to_hex();
         let sender_addr = &self.transaction.content.sender_addr.to_base58();
-        let sender_pubkey = &self.transaction.content.sender_pubkey.to_hex();
         let receiver_addr = &self.transaction.content.receiver_addr.to_base58();
         let amount = &self.transaction.content.amount;
+        let fee = &self.transaction.content.fee;
+        let nonce = &(self.transaction.content.nonce as i64);
         let timestamp = &self.transaction.content.timestamp;
         let signature = &self.transaction.signature.to_hex();
 
         conn.execute("INSERT INTO transactions(
-            id, sender_addr, sender_pubkey, receiver_addr, amount, timestamp, signature
-        ) VALUES(?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            &[&*id, &*sender_addr, &*sender_pubkey, &*receiver_addr, &*amount, &*timestamp, &*signature])?;
+            id, sender_addr, receiver_addr, amount, fee, nonce, timestamp, signature
+        ) VALUES($1, $2, $3, $4, $5, $6, $7, $8)",
+            &[&*id, &*sender_addr, &*receiver_addr, &*amount, &*fee, &*nonce, &*timestamp, &*signature])?;
 
         Ok(())
     }
@@ -140,11 +167,12 @@ impl Transaction {
 
 // create a transaction, sign it, hash it and return it
 pub fn new(
-    sender_privkey: SecretKey,
-    sender_pubkey: Vec<u8>,
+    sender_privkey: &wallet::SecretKeyBytes,
     sender_addr: Vec<u8>,
     receiver_addr: Vec<u8>,
-    amount: i32
+    amount: i32,
+    fee: i32,
+    sender_nonce: u64
 ) -> Result<Transaction, CoreError> {
     println!("CREATE TRANSACTION");
 
@@ -152,9 +180,10 @@ pub fn new(
 
     let tx_content = TransactionContent {
         sender_addr: sender_addr,
-        sender_pubkey: sender_pubkey,
         receiver_addr: receiver_addr,
         amount: amount,
+        fee: fee,
+        nonce: sender_nonce,
         timestamp: timestamp
     };
 
@@ -184,7 +213,6 @@ pub fn new(
     // TEST
     // println!("id: {}", id.to_hex());
     // println!("sender_addr: {}", tx_signed.content.sender_addr.to_base58());
-    // println!("sender_pubkey: {}", tx_signed.content.sender_pubkey.to_hex());
     // println!("receiver_addr: {}", tx_signed.content.receiver_addr.to_base58());
     // println!("amount: {}", tx_signed.content.amount);
     // println!("timestamp: {}", tx_signed.content.timestamp);
@@ -201,15 +229,15 @@ pub fn new(
 pub fn from(
     id: &String,
     sender_addr: &String,
-    sender_pubkey: &String,
     receiver_addr: &String,
     amount: i32,
+    fee: i32,
+    nonce: u64,
     timestamp: i64,
     signature: &String,
 ) -> Result<Transaction, CoreError> {
     let id: Vec<u8> = FromHex::from_hex(id)?;
     let sender_addr: Vec<u8> = sender_addr.from_base58()?;
-    let sender_pubkey: Vec<u8> = FromHex::from_hex(sender_pubkey)?;
     let receiver_addr: Vec<u8> = receiver_addr.from_base58()?;
     let signature: Vec<u8> = FromHex::from_hex(signature)?;
 
@@ -218,9 +246,10 @@ pub fn from(
         transaction: TransactionSigned {
             content: TransactionContent {
                 sender_addr: sender_addr,
-                sender_pubkey: sender_pubkey,
                 receiver_addr: receiver_addr,
                 amount: amount,
+                fee: fee,
+                nonce: nonce,
                 timestamp: timestamp
             },
             signature: signature,
@@ -229,60 +258,157 @@ pub fn from(
 }
 
 // read all cached database transactions
-pub fn read_db() -> Result<Vec<Transaction>, CoreError> {
+pub fn read_db(pool: &DbPool) -> Result<Vec<Transaction>, CoreError> {
     println!("READ TRANSACTIONS [DB]");
-    // TODO rewrite this with connection pools
-    let conn = Connection::open("db/storage.db")?;
+    let conn = pool.get()?;
 
     let mut transactions: Vec<Transaction> = Vec::new();
 
-    let mut stmt = conn.prepare(
-        "SELECT id, sender_addr, sender_pubkey, receiver_addr, amount, timestamp, signature
-        FROM transactions"
+    let rows = conn.query(
+        "SELECT id, sender_addr, receiver_addr, amount, fee, nonce, timestamp, signature
+        FROM transactions",
+        &[]
     )?;
 
-    let rows = stmt.query_map(&[], |row| {
-        let id: String = row.get(0);
-        let sender_addr: String = row.get(1);
-        let sender_pubkey: String = row.get(2);
-        let receiver_addr: String = row.get(3);
-        let amount: i32 = row.get(4);
-        let timestamp: i64 = row.get(5);
-        let signature: String = row.get(6);
-
-        Transaction {
-            id: id.into_bytes(),
-            transaction: TransactionSigned {
-                content: TransactionContent {
-                    sender_addr: sender_addr.into_bytes(),
-                    sender_pubkey: sender_pubkey.into_bytes(),
-                    receiver_addr: receiver_addr.into_bytes(),
-                    amount: amount,
-                    timestamp: timestamp
-                },
-                signature: signature.into_bytes()
-            }
-        }
-    })?;
-
-    for tx in rows {
-        transactions.push(tx?);
+    for row in &rows {
+        transactions.push(row_to_transaction(&row));
     }
 
     Ok(transactions)
 }
 
 // delete all cached transactions from database
-pub fn clean_db() -> Result<(), CoreError> {
+pub fn clean_db(pool: &DbPool) -> Result<(), CoreError> {
     println!("CLEAN TRANSACTIONS [DB]");
-    // TODO rewrite this with connection pools
-    // TODO get the db address string from config.json
-    let conn = Connection::open("db/storage.db")?;
+    let conn = pool.get()?;
 
     conn.execute("DELETE FROM transactions", &[])?;
     Ok(())
 }
 
+// read the next nonce expected from a sender; accounts that have never sent
+// a transaction start at nonce 0
+pub fn get_account_nonce(pool: &DbPool, sender_addr: &Vec<u8>) -> Result<u64, CoreError> {
+    println!("READ ACCOUNT NONCE [DB]");
+    let conn = pool.get()?;
+
+    let sender_addr = sender_addr.to_base58();
+
+    let rows = conn.query(
+        "SELECT next_nonce FROM accounts WHERE sender_addr = $1",
+        &[&sender_addr]
+    )?;
+
+    match rows.iter().next() {
+        Some(row) => {
+            let next_nonce: i64 = row.get(0);
+            Ok(next_nonce as u64)
+        },
+        None => Ok(0)
+    }
+}
+
+// advance a sender's expected nonce once a transaction using it has been
+// committed into a block, so it cannot be replayed
+pub fn advance_account_nonce(pool: &DbPool, sender_addr: &Vec<u8>, nonce: u64) -> Result<(), CoreError> {
+    println!("ADVANCE ACCOUNT NONCE [DB]");
+    let conn = pool.get()?;
+
+    let sender_addr = sender_addr.to_base58();
+    let next_nonce = (nonce + 1) as i64;
+
+    conn.execute(
+        "INSERT INTO accounts(sender_addr, next_nonce) VALUES($1, $2)
+        ON CONFLICT(sender_addr) DO UPDATE SET next_nonce = $2",
+        &[&sender_addr, &next_nonce]
+    )?;
+
+    Ok(())
+}
+
+// look up a single cached transaction by its hex-encoded id, for the
+// network::server REST API
+pub fn get_by_id(pool: &DbPool, id: &str) -> Result<Option<Transaction>, CoreError> {
+    println!("READ TRANSACTION BY ID [DB]");
+    let conn = pool.get()?;
+
+    let rows = conn.query(
+        "SELECT id, sender_addr, receiver_addr, amount, fee, nonce, timestamp, signature
+        FROM transactions WHERE id = $1",
+        &[&id]
+    )?;
+
+    Ok(rows.iter().next().map(|row| row_to_transaction(&row)))
+}
+
+// all cached transactions where the given base58 address is either the
+// sender or the receiver, for the network::server REST API
+pub fn get_by_address(pool: &DbPool, addr: &str) -> Result<Vec<Transaction>, CoreError> {
+    println!("READ TRANSACTIONS BY ADDRESS [DB]");
+    let conn = pool.get()?;
+
+    let rows = conn.query(
+        "SELECT id, sender_addr, receiver_addr, amount, fee, nonce, timestamp, signature
+        FROM transactions WHERE sender_addr = $1 OR receiver_addr = $1",
+        &[&addr]
+    )?;
+
+    let mut transactions: Vec<Transaction> = Vec::new();
+    for row in &rows {
+        transactions.push(row_to_transaction(&row));
+    }
+
+    Ok(transactions)
+}
+
+// running balance for a base58 address: inbound amounts minus outbound ones,
+// for the network::server REST API
+pub fn get_balance(pool: &DbPool, addr: &str) -> Result<i64, CoreError> {
+    println!("READ ADDRESS BALANCE [DB]");
+
+    let mut balance: i64 = 0;
+    for tx in get_by_address(pool, addr)? {
+        let amount = tx.transaction.content.amount as i64;
+
+        // row_to_transaction keeps addresses as their base58 text bytes
+        if tx.transaction.content.receiver_addr == addr.as_bytes() {
+            balance += amount;
+        }
+        if tx.transaction.content.sender_addr == addr.as_bytes() {
+            balance -= amount;
+        }
+    }
+
+    Ok(balance)
+}
+
+// shared row -> Transaction mapping for the indexed lookup queries above
+fn row_to_transaction(row: &postgres::rows::Row) -> Transaction {
+    let id: String = row.get(0);
+    let sender_addr: String = row.get(1);
+    let receiver_addr: String = row.get(2);
+    let amount: i32 = row.get(3);
+    let fee: i32 = row.get(4);
+    let nonce: i64 = row.get(5);
+    let timestamp: i64 = row.get(6);
+    let signature: String = row.get(7);
+
+    Transaction {
+        id: id.into_bytes(),
+        transaction: TransactionSigned {
+            content: TransactionContent {
+                sender_addr: sender_addr.into_bytes(),
+                receiver_addr: receiver_addr.into_bytes(),
+                amount: amount,
+                fee: fee,
+                nonce: nonce as u64,
+                timestamp: timestamp
+            },
+            signature: signature.into_bytes()
+        }
+    }
+}
+
 // // store a transaction on disk (cache) for further block creation
 // pub fn store_disk(tx: &Transaction) -> Result<(), CoreError> {
 //     println!("STORE TRANSACTION [DISK]");
@@ -343,3 +469,43 @@ pub fn clean_db() -> Result<(), CoreError> {
 //     println!("CLEAN TRANSACTIONS [DISK]");
 //     Ok(())
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::key::{PublicKey, SecretKey};
+    use wallet::SecretKeyBytes;
+
+    fn signed_tx(nonce: u64) -> Transaction {
+        let secp = secp256k1::Secp256k1::new();
+        let raw_key = [7u8; 32];
+        let secret_key = SecretKey::from_slice(&secp, &raw_key).unwrap();
+        let public_key = PublicKey::from_secret_key(&secp, &secret_key).unwrap();
+
+        let content = TransactionContent {
+            sender_addr: wallet::derive_address(&public_key),
+            receiver_addr: vec![9],
+            amount: 10,
+            fee: 1,
+            nonce: nonce,
+            timestamp: 0
+        };
+        let signature = content.get_signature(&SecretKeyBytes::new(raw_key)).unwrap();
+        let transaction = TransactionSigned { content: content, signature: signature };
+        let id = transaction.get_id().unwrap();
+
+        Transaction { id: id, transaction: transaction }
+    }
+
+    #[test]
+    fn verify_accepts_a_correctly_signed_transaction() {
+        assert!(signed_tx(0).verify().unwrap());
+    }
+
+    #[test]
+    fn verify_rejects_a_tampered_signature() {
+        let mut tx = signed_tx(0);
+        tx.transaction.signature[0] ^= 0xff;
+        assert!(!tx.verify().unwrap());
+    }
+}
@@ -0,0 +1,50 @@
+use std::fs::File;
+use std::io::Read;
+
+use sha2::{Sha256, Digest};
+use secp256k1;
+use secp256k1::key::{PublicKey, SecretKey};
+use zeroize::Zeroize;
+
+use errors::CoreError;
+
+// address = hash of the public key's compressed serialization; this is what
+// `transactions::Transaction::verify` checks a recovered public key against
+pub fn derive_address(public_key: &PublicKey) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.input(&public_key.serialize());
+    hasher.result().as_slice().to_vec()
+}
+
+// raw private key bytes, zeroed on drop
+pub struct SecretKeyBytes {
+    bytes: [u8; 32]
+}
+
+impl SecretKeyBytes {
+    pub fn new(bytes: [u8; 32]) -> SecretKeyBytes {
+        SecretKeyBytes { bytes: bytes }
+    }
+
+    pub fn from_file(path: &str) -> Result<SecretKeyBytes, CoreError> {
+        let mut file = File::open(path)?;
+        let mut bytes = [0u8; 32];
+        file.read_exact(&mut bytes)?;
+        Ok(SecretKeyBytes::new(bytes))
+    }
+
+    // only `self.bytes` is zeroed on drop; `SecretKey` isn't a zeroizing type
+    // in this secp256k1 version, so the copy parsed below still lingers in
+    // freed memory after `f` returns
+    pub fn expose<T, F: FnOnce(&SecretKey) -> T>(&self, f: F) -> Result<T, CoreError> {
+        let secp = secp256k1::Secp256k1::new();
+        let secret_key = SecretKey::from_slice(&secp, &self.bytes)?;
+        Ok(f(&secret_key))
+    }
+}
+
+impl Drop for SecretKeyBytes {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+    }
+}